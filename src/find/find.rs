@@ -1,18 +1,72 @@
 extern crate glob;
+extern crate regex;
+extern crate lscolors;
+extern crate ansi_term;
+extern crate atty;
+extern crate num_cpus;
+#[macro_use]
+extern crate lazy_static;
 
 use glob::Pattern;
 use glob::PatternError;
+use glob::MatchOptions;
+use regex::Regex;
+use regex::RegexBuilder;
+use lscolors::LsColors;
 use std::error::Error;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::fs::{self, DirEntry};
 use std::io::stderr;
 use std::io::Write;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{AtomicIsize, AtomicUsize, Ordering};
+use std::thread;
+
+/// Controls when printed paths are colorized according to `LS_COLORS`,
+/// mirroring fd's `--color auto|always|never`. "auto" only colorizes when
+/// stdout is a TTY, so redirecting `find`'s output to a file or pipe keeps
+/// it plain.
+#[derive(Clone, Copy)]
+enum ColorMode {
+  Auto,
+  Always,
+  Never,
+}
+
+impl ColorMode {
+  fn from_str(s : &str) -> Result<ColorMode, Box<Error>> {
+    match s {
+      "auto" => Ok(ColorMode::Auto),
+      "always" => Ok(ColorMode::Always),
+      "never" => Ok(ColorMode::Never),
+      _ => Err(From::from(format!("Unknown argument to -color: {}", s))),
+    }
+  }
+
+  fn enabled(&self) -> bool {
+    match *self {
+      ColorMode::Always => true,
+      ColorMode::Never => false,
+      ColorMode::Auto => atty::is(atty::Stream::Stdout),
+    }
+  }
+}
+
+lazy_static! {
+  /// Guards stdout so that concurrent worker threads printing matches don't
+  /// interleave their output.
+  static ref PRINT_LOCK: Mutex<()> = Mutex::new(());
+}
 
 /// A basic interface that can be used to determine whether a directory entry
 /// is what's being searched for. To a first order approximation, find consists
 /// of building a chain of Matcher objets, and then w,alking a directory tree,
 /// passing each entry to the chaing of Matchers.
-trait Matcher {
+///
+/// Traversal runs on a pool of worker threads (see `parallel_walk`), so
+/// every Matcher must be safe to share and call concurrently.
+trait Matcher: Send + Sync {
   /// Returns whether the given file matches the object's predicate.
   fn matches(&self, file_info: &DirEntry) -> bool;
 
@@ -22,16 +76,40 @@ trait Matcher {
   /// that contain a collection of sub-Matchers.
   fn has_side_effects(&self) -> bool;
 
+  /// Called once traversal has finished visiting every entry. Matchers that
+  /// batch up work across calls to `matches` (such as the `-exec ... +`
+  /// form of ExecMatcher) override this to flush what they've accumulated.
+  fn finish(&self) {
+  }
+
 }
 
 
-/// This matcher just prints the name of the file to stdout.
-struct Printer {}
+/// This matcher just prints the name of the file to stdout, optionally
+/// colorizing path components according to `LS_COLORS` the way fd does.
+struct Printer {
+  ls_colors : Option<LsColors>,
+}
+
+impl Printer {
+  fn new(color_mode : ColorMode) -> Printer {
+    let ls_colors = if color_mode.enabled() {
+      Some(LsColors::from_env().unwrap_or_default())
+    } else {
+      None
+    };
+    Printer{ ls_colors : ls_colors }
+  }
+}
 
 impl Matcher for Printer {
  fn matches(&self, file_info: &DirEntry) -> bool {
    if let Some(x) = file_info.path().to_str() {
-    println!("{}", x);
+    let _guard = PRINT_LOCK.lock().unwrap();
+    match self.ls_colors {
+      Some(ref ls_colors) => print_colorized(&file_info.path(), ls_colors),
+      None => println!("{}", x),
+    }
    }
    true
   }
@@ -41,7 +119,95 @@ impl Matcher for Printer {
   }
 }
 
-/// This matcher makes a case-sensitive comparison of the name against a 
+/// Prints `path` with each path component styled individually, walking the
+/// path component-by-component (as fd's `print_entry` does) so that, e.g., a
+/// parent directory is colored as a directory even when the final component
+/// is a regular file. Callers must hold `PRINT_LOCK`.
+fn print_colorized(path : &Path, ls_colors : &LsColors) {
+  let mut component_path = PathBuf::new();
+  // RootDir's own component text already ends in a separator (e.g. "/" on
+  // unix), so don't print another one before it -- or before whatever
+  // follows it.
+  let mut prev_ended_with_sep = true;
+  for component in path.components() {
+    component_path.push(component.as_os_str());
+    let component_str = component.as_os_str().to_string_lossy();
+    if !prev_ended_with_sep {
+      print!("{}", std::path::MAIN_SEPARATOR);
+    }
+    prev_ended_with_sep = component_str.ends_with(std::path::MAIN_SEPARATOR);
+    let metadata = component_path.symlink_metadata().ok();
+    match ls_colors.style_for_path_with_metadata(&component_path, metadata.as_ref()) {
+      Some(style) => print!("{}", style.to_ansi_term_style().paint(component_str)),
+      None => print!("{}", component_str),
+    }
+  }
+  println!("");
+}
+
+/// This matcher prints the name of the file to stdout followed by a NUL
+/// byte instead of a newline, so that output can be safely piped to
+/// `xargs -0` even when filenames contain spaces or newlines. Since that
+/// means writing a byte that isn't valid to embed in a `&str`, this writes
+/// the path's raw OsStr bytes directly rather than going through `println!`.
+struct NullPrinter {}
+
+impl Matcher for NullPrinter {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    let _guard = PRINT_LOCK.lock().unwrap();
+    write_null_terminated(&file_info.path());
+    true
+  }
+
+  fn has_side_effects(&self) -> bool {
+    true
+  }
+}
+
+#[cfg(unix)]
+fn write_null_terminated(path : &Path) {
+  use std::os::unix::ffi::OsStrExt;
+  let stdout = std::io::stdout();
+  let mut handle = stdout.lock();
+  handle.write_all(path.as_os_str().as_bytes()).unwrap();
+  handle.write_all(b"\0").unwrap();
+}
+
+#[cfg(not(unix))]
+fn write_null_terminated(path : &Path) {
+  let stdout = std::io::stdout();
+  let mut handle = stdout.lock();
+  handle.write_all(path.to_string_lossy().as_bytes()).unwrap();
+  handle.write_all(b"\0").unwrap();
+}
+
+/// These always match / never match, regardless of the entry, mirroring GNU
+/// find's `-true` and `-false` test predicates. Neither has side effects.
+struct TrueMatcher {}
+
+impl Matcher for TrueMatcher {
+  fn matches(&self, _file_info: &DirEntry) -> bool {
+    true
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+}
+
+struct FalseMatcher {}
+
+impl Matcher for FalseMatcher {
+  fn matches(&self, _file_info: &DirEntry) -> bool {
+    false
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+}
+
+/// This matcher makes a case-sensitive comparison of the name against a
 /// shell wildcard pattern. See glob::Pattern for details on the exact
 /// syntax.
 pub struct NameMatcher {
@@ -69,7 +235,7 @@ impl Matcher for NameMatcher {
   }
 }
 
-/// This matcher makes a case-insensitive comparison of the name against a 
+/// This matcher makes a case-insensitive comparison of the name against a
 /// shell wildcard pattern. See glob::Pattern for details on the exact
 /// syntax.
 struct CaselessNameMatcher {
@@ -97,16 +263,299 @@ impl Matcher for CaselessNameMatcher {
   }
 }
 
+/// This matcher makes a case-sensitive comparison of the entry's whole path
+/// (GNU find's `-path`/`-wholename`) against a shell wildcard pattern,
+/// rather than just its file name like NameMatcher. Because glob::Pattern's
+/// `**` component matches the current directory and arbitrarily many
+/// intervening directory components, this also lets patterns like
+/// `**/test/*.rs` match regardless of nesting depth; `**` must still form
+/// its own path component, so e.g. `a**` is rejected by Pattern::new just
+/// as it is for `-name`.
+struct PathMatcher {
+  pattern: Pattern,
+}
+
+impl PathMatcher {
+  fn new(pattern_string : &String) -> Result<PathMatcher, PatternError> {
+    let p = try!(Pattern::new(pattern_string));
+    Ok(PathMatcher{ pattern : p })
+  }
+}
+
+impl Matcher for PathMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    if let Some(x) = file_info.path().to_str() {
+      return self.pattern.matches_with(x, path_match_options());
+    }
+    false
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+}
+
+/// `require_literal_separator: true` makes a lone `*`/`?` stop at a path
+/// separator, so only an explicit `**` component crosses directories --
+/// otherwise glob's default options let ordinary wildcards match across
+/// separators too, defeating the distinction this matcher exists to draw.
+fn path_match_options() -> MatchOptions {
+  MatchOptions{ require_literal_separator: true, .. Default::default() }
+}
+
+/// This matcher makes a case-insensitive comparison of the entry's whole
+/// path (GNU find's `-ipath`) against a shell wildcard pattern. See
+/// PathMatcher for the `**` recursive-glob semantics.
+struct CaselessPathMatcher {
+  pattern: Pattern,
+}
+
+impl CaselessPathMatcher {
+  fn new(pattern_string : &String) -> Result<CaselessPathMatcher, PatternError> {
+    let p = try!(Pattern::new(&pattern_string.to_lowercase()));
+    Ok(CaselessPathMatcher{ pattern : p })
+  }
+}
+
+impl Matcher for CaselessPathMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    if let Some(x) = file_info.path().to_str() {
+      return self.pattern.matches_with(&x.to_lowercase(), path_match_options());
+    }
+    false
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+}
+
+/// This matcher tests a compiled regular expression against the entry's
+/// whole path (not just its file name, unlike NameMatcher/CaselessNameMatcher
+/// -- this mirrors GNU find's `-regex` semantics). The pattern is anchored
+/// to the whole path at construction time, since regex::Regex::is_match
+/// otherwise accepts an unanchored substring match, which GNU `-regex` does
+/// not. The regex is compiled once via RegexBuilder, optionally
+/// case-insensitively.
+struct RegexMatcher {
+  regex: Regex,
+}
+
+impl RegexMatcher {
+  fn new(pattern_string : &String, case_insensitive : bool) -> Result<RegexMatcher, regex::Error> {
+    let anchored = format!("^(?:{})$", pattern_string);
+    let r = try!(RegexBuilder::new(&anchored)
+      .case_insensitive(case_insensitive)
+      .build());
+    Ok(RegexMatcher{ regex : r })
+  }
+}
+
+impl Matcher for RegexMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    if let Some(x) = file_info.path().to_str() {
+      return self.regex.is_match(x);
+    }
+    false
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+}
+
+/// This matcher tests the kind of a directory entry (regular file,
+/// directory, symlink, etc.) against a single type letter as accepted by
+/// GNU find's `-type`: f, d, l, b, c, p or s. `symlink_metadata` is used so
+/// that symlinks are inspected directly rather than followed, which matters
+/// for telling `l` apart from whatever the link points at.
+struct TypeMatcher {
+  type_char : char,
+}
+
+impl TypeMatcher {
+  fn new(type_char : char) -> Result<TypeMatcher, Box<std::error::Error>> {
+    match type_char {
+      'f' | 'd' | 'l' | 'b' | 'c' | 'p' | 's' => Ok(TypeMatcher{ type_char : type_char }),
+      _ => Err(From::from(format!("Unknown argument to -type: {}", type_char))),
+    }
+  }
+}
+
+impl Matcher for TypeMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    let metadata = match file_info.path().symlink_metadata() {
+      Ok(m) => m,
+      Err(_) => return false,
+    };
+    let file_type = metadata.file_type();
+    match self.type_char {
+      'f' => file_type.is_file(),
+      'd' => file_type.is_dir(),
+      'l' => file_type.is_symlink(),
+      'b' => is_block_device(&file_type),
+      'c' => is_char_device(&file_type),
+      'p' => is_fifo(&file_type),
+      's' => is_socket(&file_type),
+      _ => false,
+    }
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+}
+
+#[cfg(unix)]
+fn is_block_device(file_type : &std::fs::FileType) -> bool {
+  use std::os::unix::fs::FileTypeExt;
+  file_type.is_block_device()
+}
+
+#[cfg(unix)]
+fn is_char_device(file_type : &std::fs::FileType) -> bool {
+  use std::os::unix::fs::FileTypeExt;
+  file_type.is_char_device()
+}
+
+#[cfg(unix)]
+fn is_fifo(file_type : &std::fs::FileType) -> bool {
+  use std::os::unix::fs::FileTypeExt;
+  file_type.is_fifo()
+}
+
+#[cfg(unix)]
+fn is_socket(file_type : &std::fs::FileType) -> bool {
+  use std::os::unix::fs::FileTypeExt;
+  file_type.is_socket()
+}
+
+#[cfg(not(unix))]
+fn is_block_device(_file_type : &std::fs::FileType) -> bool { false }
+#[cfg(not(unix))]
+fn is_char_device(_file_type : &std::fs::FileType) -> bool { false }
+#[cfg(not(unix))]
+fn is_fifo(_file_type : &std::fs::FileType) -> bool { false }
+#[cfg(not(unix))]
+fn is_socket(_file_type : &std::fs::FileType) -> bool { false }
+
+/// Maximum number of paths bundled into a single invocation of the `+` form
+/// of -exec/-execdir, mirroring the batching xargs does to stay well clear
+/// of the OS argument-list length limit.
+const EXEC_BATCH_SIZE : usize = 512;
+
+/// Whether -exec/-execdir runs its command once per matched file (the `;`
+/// terminator) or accumulates matches and runs in batches (the `+`
+/// terminator).
+enum ExecMode {
+  PerFile,
+  Batched,
+}
+
+/// This matcher runs an external command for each matched file, modeled on
+/// fd's CommandTemplate. `{}` in the command template is substituted with
+/// the entry's path (or, for -execdir, just its basename, with the child
+/// process's working directory set to the entry's parent directory). Since
+/// matches() can be called concurrently by traversal worker threads, the
+/// `+` form's accumulated paths are kept behind a Mutex and only spawned
+/// once traversal completes, in `finish`.
+struct ExecMatcher {
+  template : Vec<String>,
+  mode : ExecMode,
+  execdir : bool,
+  batch : Mutex<Vec<PathBuf>>,
+}
+
+impl ExecMatcher {
+  fn new(template : Vec<String>, mode : ExecMode, execdir : bool) -> ExecMatcher {
+    ExecMatcher{ template : template, mode : mode, execdir : execdir, batch : Mutex::new(Vec::new()) }
+  }
+
+  fn run_one(&self, path : &Path) {
+    let (replacement, cwd) = if self.execdir {
+      let basename = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+      (basename, path.parent().map(|p| p.to_path_buf()))
+    } else {
+      (path.to_string_lossy().into_owned(), None)
+    };
+    let args : Vec<String> = self.template[1 ..].iter()
+      .map(|arg| if arg == "{}" { replacement.clone() } else { arg.clone() })
+      .collect();
+    run_command(&self.template[0], &args, cwd.as_ref().map(|p| p.as_path()));
+  }
+
+  fn run_batch(&self, program_args : &[String], basenames : &[String], cwd : Option<&Path>) {
+    for chunk in basenames.chunks(EXEC_BATCH_SIZE) {
+      let mut args = program_args.to_vec();
+      args.extend(chunk.iter().cloned());
+      run_command(&self.template[0], &args, cwd);
+    }
+  }
+}
+
+/// Spawns `program` with `args`, optionally in `cwd`, waiting for it to
+/// finish. A failure to even launch the command is reported but does not
+/// abort the traversal, matching find's treatment of other per-entry errors.
+fn run_command(program : &str, args : &[String], cwd : Option<&Path>) {
+  let mut command = std::process::Command::new(program);
+  command.args(args);
+  if let Some(dir) = cwd {
+    command.current_dir(dir);
+  }
+  if let Err(e) = command.status() {
+    writeln!(&mut stderr(), "Error: {}: {}", program, e).unwrap();
+  }
+}
+
+impl Matcher for ExecMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    let path = file_info.path();
+    match self.mode {
+      ExecMode::PerFile => self.run_one(&path),
+      ExecMode::Batched => self.batch.lock().unwrap().push(path),
+    }
+    true
+  }
+
+  fn has_side_effects(&self) -> bool {
+    true
+  }
+
+  fn finish(&self) {
+    let paths = self.batch.lock().unwrap();
+    if paths.is_empty() {
+      return;
+    }
+    // The template's trailing "{}" is where the accumulated paths get
+    // appended, so everything before it forms the fixed argument prefix.
+    let program_args = &self.template[1 .. self.template.len() - 1];
+    if self.execdir {
+      let mut by_dir : std::collections::BTreeMap<PathBuf, Vec<String>> = std::collections::BTreeMap::new();
+      for path in paths.iter() {
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let basename = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        by_dir.entry(dir).or_insert_with(Vec::new).push(basename);
+      }
+      for (dir, basenames) in by_dir {
+        self.run_batch(program_args, &basenames, Some(&dir));
+      }
+    } else {
+      let all_paths : Vec<String> = paths.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+      self.run_batch(program_args, &all_paths, None);
+    }
+  }
+}
+
 /// This matcher contains a collection of other matchers. A file only matches
-/// if it matches ALL the contained sub-matchers. For sub-matchers that have 
+/// if it matches ALL the contained sub-matchers. For sub-matchers that have
 /// side effects, the side effects occur in the same order as the sub-matchers
 /// were pushed into the collection.
 struct AndMatcher<> {
-  submatchers : Vec<Box<Matcher>>,
+  submatchers : Vec<Arc<Matcher>>,
 }
 
 impl AndMatcher {
-  fn push(&mut self, matcher : Box<Matcher>) {
+  fn push(&mut self, matcher : Arc<Matcher>) {
     self.submatchers.push(matcher);
   }
 
@@ -136,103 +585,483 @@ impl Matcher for AndMatcher {
     }
     false
   }
+
+  fn finish(&self) {
+    for matcher in &self.submatchers {
+      matcher.finish();
+    }
+  }
+}
+
+/// This matcher contains a collection of other matchers, joined by OR.
+/// A file matches if ANY of the contained sub-matchers match it, and
+/// evaluation short-circuits at the first matching sub-matcher (so later
+/// sub-matchers' side effects will not run for that file).
+struct OrMatcher {
+  submatchers : Vec<Arc<Matcher>>,
 }
 
+impl OrMatcher {
+  fn push(&mut self, matcher : Arc<Matcher>) {
+    self.submatchers.push(matcher);
+  }
 
-/// Builds a single AndMatcher containing the Matcher objects corresponding
-/// to the passed in predicate arguments.
-fn build_top_level_matcher(args : &[String]) -> Result<Box<Matcher>, Box<std::error::Error>> {
-  let mut top_level_matcher = AndMatcher::new();
+  fn new() -> OrMatcher {
+    OrMatcher{
+      submatchers : Vec::new()
+    }
+  }
+}
 
-  // can't use getopts for a variety or reasons:
-  // order ot arguments is important
-  // arguments can start with + as well as -
-  // multiple-character flags don't start with a double dash
-  let mut i = 0;
-  while i < args.len() {
-    let submatcher = match args[i].as_ref() {
-      "-print" => Box::new(Printer{}) as Box<Matcher>,
+impl Matcher for OrMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    for matcher in &self.submatchers{
+      if matcher.matches(file_info) {
+        return true;
+      }
+    }
+    false
+  }
+
+  fn has_side_effects(&self) -> bool {
+    for matcher in &self.submatchers{
+      if matcher.has_side_effects() {
+        return true;
+      }
+    }
+    false
+  }
+
+  fn finish(&self) {
+    for matcher in &self.submatchers {
+      matcher.finish();
+    }
+  }
+}
+
+/// This matcher wraps another matcher and inverts its result. Since negating
+/// a side-effecting matcher would make the side effect run on every file that
+/// *doesn't* match the wrapped matcher (which isn't how find's `-not` works,
+/// and would otherwise suppress the implicit -print for the wrong reason),
+/// a NotMatcher always reports itself as free of side effects.
+struct NotMatcher {
+  submatcher : Arc<Matcher>,
+}
+
+impl NotMatcher {
+  fn new(matcher : Arc<Matcher>) -> NotMatcher {
+    NotMatcher{ submatcher : matcher }
+  }
+}
+
+impl Matcher for NotMatcher {
+  fn matches(&self, file_info: &DirEntry) -> bool {
+    !self.submatcher.matches(file_info)
+  }
+
+  fn has_side_effects(&self) -> bool {
+    false
+  }
+
+  fn finish(&self) {
+    self.submatcher.finish();
+  }
+}
+
+/// Parses a slice of predicate arguments into a Matcher tree.
+///
+/// The grammar (highest to lowest precedence) is:
+///   primary := '(' or_expr ')' | leaf
+///   not_expr := ('-not' | '!') not_expr | primary
+///   and_expr := not_expr (('-a' | '-and')? not_expr)*
+///   or_expr := and_expr (('-o' | '-or') and_expr)*
+///
+/// i.e. `-not`/`!` binds tightest, adjacency and `-a`/`-and` form AND, and
+/// `-o`/`-or` forms OR with the lowest precedence.
+struct ExpressionParser<'a> {
+  args : &'a [String],
+  pos : usize,
+  color_mode : ColorMode,
+}
+
+impl<'a> ExpressionParser<'a> {
+  fn new(args : &'a [String], color_mode : ColorMode) -> ExpressionParser<'a> {
+    ExpressionParser{ args : args, pos : 0, color_mode : color_mode }
+  }
+
+  fn peek(&self) -> Option<&str> {
+    self.args.get(self.pos).map(|s| s.as_ref())
+  }
+
+  fn advance(&mut self) -> Option<&'a String> {
+    let arg = self.args.get(self.pos);
+    self.pos += 1;
+    arg
+  }
+
+  fn parse_or(&mut self) -> Result<Arc<Matcher>, Box<std::error::Error>> {
+    let mut or_matcher = OrMatcher::new();
+    or_matcher.push(try!(self.parse_and()));
+    loop {
+      match self.peek() {
+        Some("-o") | Some("-or") => {
+          self.pos += 1;
+          or_matcher.push(try!(self.parse_and()));
+        },
+        _ => break,
+      }
+    }
+    Ok(Arc::new(or_matcher))
+  }
+
+  fn parse_and(&mut self) -> Result<Arc<Matcher>, Box<std::error::Error>> {
+    let mut and_matcher = AndMatcher::new();
+    and_matcher.push(try!(self.parse_not()));
+    loop {
+      match self.peek() {
+        Some("-a") | Some("-and") => {
+          self.pos += 1;
+          and_matcher.push(try!(self.parse_not()));
+        },
+        Some(")") | Some("-o") | Some("-or") | None => break,
+        _ => and_matcher.push(try!(self.parse_not())),
+      }
+    }
+    Ok(Arc::new(and_matcher))
+  }
+
+  fn parse_not(&mut self) -> Result<Arc<Matcher>, Box<std::error::Error>> {
+    match self.peek() {
+      Some("-not") | Some("!") => {
+        self.pos += 1;
+        Ok(Arc::new(NotMatcher::new(try!(self.parse_not()))))
+      },
+      _ => self.parse_primary(),
+    }
+  }
+
+  fn parse_primary(&mut self) -> Result<Arc<Matcher>, Box<std::error::Error>> {
+    match self.peek() {
+      Some("(") => {
+        self.pos += 1;
+        let inner = try!(self.parse_or());
+        match self.advance().map(|s| s.as_ref()) {
+          Some(")") => Ok(inner),
+          _ => Err(From::from("Unmatched '(' in expression")),
+        }
+      },
+      Some(")") => Err(From::from("Unexpected ')' in expression")),
+      Some(_) => self.parse_leaf(),
+      None => Err(From::from("Expected an expression")),
+    }
+  }
+
+  fn parse_leaf(&mut self) -> Result<Arc<Matcher>, Box<std::error::Error>> {
+    let arg = self.advance().unwrap().clone();
+    let submatcher : Arc<Matcher> = match arg.as_ref() {
+      "-print" => Arc::new(Printer::new(self.color_mode)),
+      "-print0" => Arc::new(NullPrinter{}),
+      "-true" => Arc::new(TrueMatcher{}),
+      "-false" => Arc::new(FalseMatcher{}),
       "-name" => {
-          i += 1;
-          if i >= args.len() {
-            return Err(From::from("Must supply a pattern with -name"));
-          }
-          Box::new(try!(NameMatcher::new(&args[i])))
+          let pattern = try!(self.next_value("-name"));
+          Arc::new(try!(NameMatcher::new(pattern)))
         },
       "-iname" => {
-          i += 1;
-          if i >= args.len() {
-            return Err(From::from("Must supply a pattern with -iname"));
-          }
-          Box::new(try!(CaselessNameMatcher::new(&args[i])))
+          let pattern = try!(self.next_value("-iname"));
+          Arc::new(try!(CaselessNameMatcher::new(pattern)))
+        },
+      "-regex" => {
+          let pattern = try!(self.next_value("-regex"));
+          Arc::new(try!(RegexMatcher::new(pattern, false)))
+        },
+      "-iregex" => {
+          let pattern = try!(self.next_value("-iregex"));
+          Arc::new(try!(RegexMatcher::new(pattern, true)))
+        },
+      "-type" => {
+          let type_string = try!(self.next_value("-type"));
+          let type_char = match type_string.chars().next() {
+            Some(c) if type_string.len() == 1 => c,
+            _ => return Err(From::from(format!("Unknown argument to -type: {}", type_string))),
+          };
+          Arc::new(try!(TypeMatcher::new(type_char)))
+        },
+      "-path" | "-wholename" => {
+          let pattern = try!(self.next_value(arg.as_ref()));
+          Arc::new(try!(PathMatcher::new(pattern)))
+        },
+      "-ipath" => {
+          let pattern = try!(self.next_value("-ipath"));
+          Arc::new(try!(CaselessPathMatcher::new(pattern)))
         },
-      _ => return Err(From::from(format!("Unrecognized flag: '{}'", args[i])))
+      "-exec" => Arc::new(try!(self.parse_exec(false))),
+      "-execdir" => Arc::new(try!(self.parse_exec(true))),
+      _ => return Err(From::from(format!("Unrecognized flag: '{}'", arg))),
     };
-    top_level_matcher.push(submatcher);
-    i += 1;
+    Ok(submatcher)
+  }
+
+  /// Consumes the command template following `-exec`/`-execdir` up to its
+  /// terminating `;` (run once per match) or `+` (accumulate and batch).
+  fn parse_exec(&mut self, execdir : bool) -> Result<ExecMatcher, Box<std::error::Error>> {
+    let flag = if execdir { "-execdir" } else { "-exec" };
+    let mut template : Vec<String> = Vec::new();
+    let mode = loop {
+      match self.advance() {
+        Some(tok) if tok == ";" => break ExecMode::PerFile,
+        Some(tok) if tok == "+" => break ExecMode::Batched,
+        Some(tok) => template.push(tok.clone()),
+        None => return Err(From::from(
+          format!("{} must be terminated with ';' or '+'", flag))),
+      }
+    };
+    if template.is_empty() {
+      return Err(From::from(format!("Must supply a command with {}", flag)));
+    }
+    if let ExecMode::Batched = mode {
+      let placeholder_count = template.iter().filter(|tok| tok.as_str() == "{}").count();
+      if placeholder_count != 1 || template.last().map(|t| t.as_str()) != Some("{}") {
+        return Err(From::from(
+          format!("{} ... + must have exactly one '{{}}', as its last argument", flag)));
+      }
+    }
+    Ok(ExecMatcher::new(template, mode, execdir))
+  }
+
+  fn next_value(&mut self, flag : &str) -> Result<&'a String, Box<std::error::Error>> {
+    match self.advance() {
+      Some(value) => Ok(value),
+      None => Err(From::from(format!("Must supply a pattern with {}", flag))),
+    }
+  }
+}
+
+/// Builds a Matcher tree containing the Matcher objects corresponding
+/// to the passed in predicate arguments, honoring `-o`/`-or`, `-not`/`!`
+/// and `(`/`)` grouping as well as implicit/explicit AND.
+fn build_top_level_matcher(args : &[String], color_mode : ColorMode)
+  -> Result<Arc<Matcher>, Box<std::error::Error>> {
+  if args.is_empty() {
+    return Ok(Arc::new(Printer::new(color_mode)));
+  }
+
+  let mut parser = ExpressionParser::new(args, color_mode);
+  let top_level_matcher = try!(parser.parse_or());
+  if parser.pos != args.len() {
+    return Err(From::from(format!("Unexpected token: '{}'", args[parser.pos])));
   }
 
   if !top_level_matcher.has_side_effects() {
-    top_level_matcher.push(Box::new(Printer{}));
+    let mut with_print = AndMatcher::new();
+    with_print.push(top_level_matcher);
+    with_print.push(Arc::new(Printer::new(color_mode)));
+    return Ok(Arc::new(with_print));
   }
-  Ok(Box::new(top_level_matcher))
+  Ok(top_level_matcher)
 }
 
 struct PathsAndMatcher {
-  matcher : Box<Matcher>,
+  matcher : Arc<Matcher>,
   paths : Vec<String>,
+  mindepth : usize,
+  maxdepth : Option<usize>,
+}
+
+/// Parses a `usize` value for a depth flag, producing the same style of
+/// error as a missing/invalid pattern argument.
+fn parse_depth_value(flag : &str, value : Option<&String>) -> Result<usize, Box<Error>> {
+  let value = match value {
+    Some(v) => v,
+    None => return Err(From::from(format!("Must supply a value with {}", flag))),
+  };
+  match value.parse::<usize>() {
+    Ok(n) => Ok(n),
+    Err(_) => Err(From::from(format!("Invalid argument to {}: {}", flag, value))),
+  }
 }
 
 fn parse_args(args : &[String]) -> Result<PathsAndMatcher, Box<Error>> {
   let mut paths : Vec<String> = Vec::new();
   let mut i = 0;
 
-  while i < args.len() && !args[i].starts_with('-') {
+  while i < args.len() && !args[i].starts_with('-')
+    && args[i] != "(" && args[i] != "!" {
     paths.push(args[i].clone());
     i += 1;
   }
   if i == 0 {
     paths.push(".".to_string());
   }
-  let matcher = try!(build_top_level_matcher(&args[i ..]));
-  Ok(PathsAndMatcher{ matcher : matcher, paths : paths})
+
+  // -maxdepth/-mindepth/-color are global options rather than expression
+  // predicates, so pull them out of the remaining args before handing the
+  // rest to the expression parser.
+  let mut mindepth = 0;
+  let mut maxdepth = None;
+  let mut color_mode = ColorMode::Auto;
+  let mut predicate_args : Vec<String> = Vec::new();
+  let mut j = i;
+  while j < args.len() {
+    match args[j].as_ref() {
+      "-maxdepth" => {
+        j += 1;
+        maxdepth = Some(try!(parse_depth_value("-maxdepth", args.get(j))));
+      },
+      "-mindepth" => {
+        j += 1;
+        mindepth = try!(parse_depth_value("-mindepth", args.get(j)));
+      },
+      "-color" => {
+        j += 1;
+        let mode_string = match args.get(j) {
+          Some(s) => s,
+          None => return Err(From::from("Must supply a mode with -color")),
+        };
+        color_mode = try!(ColorMode::from_str(mode_string));
+      },
+      _ => predicate_args.push(args[j].clone()),
+    }
+    j += 1;
+  }
+
+  let matcher = try!(build_top_level_matcher(&predicate_args, color_mode));
+  Ok(PathsAndMatcher{
+    matcher : matcher,
+    paths : paths,
+    mindepth : mindepth,
+    maxdepth : maxdepth,
+  })
 }
 
-fn process_dir(dir : &Path, matcher : &Box<Matcher>) 
-  -> Result<i32, Box<Error>> {
-  let mut found_count = 0;
+/// State shared by the worker threads that drive a parallel traversal: a
+/// work-stealing queue of directories still to visit, plus the counters
+/// needed to know when every worker has run dry.
+struct WalkState {
+  queue : Mutex<VecDeque<(PathBuf, usize)>>,
+  queue_cv : Condvar,
+  // Number of directories that are queued or currently being processed.
+  // Traversal is complete once this reaches zero and the queue is empty.
+  pending : AtomicUsize,
+  found_count : AtomicIsize,
+  matcher : Arc<Matcher>,
+  mindepth : usize,
+  maxdepth : Option<usize>,
+}
+
+impl WalkState {
+  fn push_dir(&self, dir : PathBuf, depth : usize) {
+    self.pending.fetch_add(1, Ordering::SeqCst);
+    self.queue.lock().unwrap().push_back((dir, depth));
+    self.queue_cv.notify_all();
+  }
+}
+
+/// Reads the entries of `dir`, matching each one and queuing any
+/// subdirectories for later processing. An unreadable directory or entry is
+/// reported on stderr and traversal carries on with the rest.
+fn process_dir_entries(state : &WalkState, dir : &Path, depth : usize) {
   match fs::read_dir(dir) {
     Ok(entry_results) => {
       for entry_result in entry_results {
-        let entry = try!(entry_result);
-            let path : std::path::PathBuf = entry.path();
-            if matcher.matches(&entry) {
-              found_count += 1;
-            }
-            if path.is_dir() {
-                try!(process_dir(&path, matcher));
-            }
+        let entry = match entry_result {
+          Ok(entry) => entry,
+          Err(e) => {
+            writeln!(&mut stderr(), "Error: {}: {}", dir.to_string_lossy(), e).unwrap();
+            continue;
+          },
+        };
+        let path = entry.path();
+        // Entries found while scanning a directory at `depth` are themselves
+        // one level deeper than that directory.
+        let entry_depth = depth + 1;
+        let within_maxdepth = state.maxdepth.map_or(true, |max| entry_depth <= max);
+        if entry_depth >= state.mindepth && within_maxdepth && state.matcher.matches(&entry) {
+          state.found_count.fetch_add(1, Ordering::SeqCst);
+        }
+        if path.is_dir() && state.maxdepth.map_or(true, |max| entry_depth < max) {
+          state.push_dir(path, entry_depth);
+        }
       }
     },
     Err(e) => {
-      writeln!(&mut stderr(), 
-        "Error: {}: {}", 
+      writeln!(&mut stderr(),
+        "Error: {}: {}",
         dir.to_string_lossy(), e.description()).unwrap();
     }
   }
-  Ok(found_count)
+}
+
+/// Pops and processes directories from the shared queue until traversal is
+/// complete, parking on the condvar whenever the queue is momentarily empty
+/// but other workers might still discover more work.
+fn worker_loop(state : Arc<WalkState>) {
+  loop {
+    let next = {
+      let mut queue = state.queue.lock().unwrap();
+      loop {
+        if let Some(item) = queue.pop_front() {
+          break Some(item);
+        }
+        if state.pending.load(Ordering::SeqCst) == 0 {
+          break None;
+        }
+        queue = state.queue_cv.wait(queue).unwrap();
+      }
+    };
+    let (dir, depth) = match next {
+      Some(item) => item,
+      None => return,
+    };
+    process_dir_entries(&state, &dir, depth);
+    if state.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+      // We just brought `pending` down to zero: wake any workers parked
+      // waiting for more work so they can observe completion and exit.
+      state.queue_cv.notify_all();
+    }
+  }
+}
+
+/// Walks `paths` in parallel using a pool of worker threads sized to the
+/// number of CPUs, modeled on fd's use of a work-stealing queue over
+/// ignore::WalkBuilder. Matcher side effects (e.g. Printer) serialize their
+/// output via PRINT_LOCK so concurrent matches don't interleave.
+fn parallel_walk(paths : Vec<String>, matcher : Arc<Matcher>, mindepth : usize,
+  maxdepth : Option<usize>) -> Result<i32, Box<Error>> {
+  let state = Arc::new(WalkState{
+    queue : Mutex::new(VecDeque::new()),
+    queue_cv : Condvar::new(),
+    pending : AtomicUsize::new(0),
+    found_count : AtomicIsize::new(0),
+    matcher : matcher,
+    mindepth : mindepth,
+    maxdepth : maxdepth,
+  });
+
+  for path in paths {
+    state.push_dir(PathBuf::from(path), 0);
+  }
+
+  let num_workers = num_cpus::get();
+  let handles : Vec<_> = (0 .. num_workers).map(|_| {
+    let state = state.clone();
+    thread::spawn(move || worker_loop(state))
+  }).collect();
+
+  for handle in handles {
+    handle.join().unwrap();
+  }
+
+  state.matcher.finish();
+  Ok(state.found_count.load(Ordering::SeqCst) as i32)
 }
 
 
 fn do_find(args : &[String]) -> Result<i32, Box<Error>> {
 
   let paths_and_matcher = try!(parse_args(args));
-  let mut found_count = 0;
-  for path in paths_and_matcher.paths {
-    let dir = Path::new(&path);
-    found_count += try!(process_dir(&dir, &paths_and_matcher.matcher));
-  }
-  Ok(found_count)
+  parallel_walk(paths_and_matcher.paths, paths_and_matcher.matcher,
+    paths_and_matcher.mindepth, paths_and_matcher.maxdepth)
 }
 
 fn print_help() {
@@ -244,6 +1073,19 @@ Early alpha implementation. Currently the only expressions supported are
  -print
  -name case-sensitive_filename_pattern
  -iname case-insensitive_filename_pattern
+ -regex case-sensitive_path_regex
+ -iregex case-insensitive_path_regex
+ -type [f|d|l|b|c|p|s]
+ -path/-wholename case-sensitive_path_pattern (supports ** recursive glob)
+ -ipath case-insensitive_path_pattern
+ -not, ! / -a, -and / -o, -or / ( expr )
+ -maxdepth N
+ -mindepth N
+ -color [auto|always|never]
+ -exec command {{}} ; / -exec command {{}} +
+ -execdir command {{}} ; / -execdir command {{}} +
+ -print0
+ -true / -false
 ");
 }
 
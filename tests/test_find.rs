@@ -54,10 +54,83 @@ fn test_bad_flag_fails() {
 
 #[test]
 fn test_bad_directory_detected_but_continues() {
-	
+
 	println!("CWD is {}", env::current_dir().unwrap().display());
     new_ucmd!()
         .args(&["./simple", "bad_directory"])
         .succeeds()
         .stderr_is("Error: bad_directory: entity not found")
-        .stdout_is("./simple/abbc");}
\ No newline at end of file
+        .stdout_is("./simple/abbc");}
+
+#[test]
+fn test_or_matches_either_branch() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    new_ucmd!()
+        .args(&["./simple", "-name", "zzz", "-o", "-name", "a*c"])
+        .succeeds()
+        .stdout_only("./simple/abbc");
+}
+
+#[test]
+fn test_not_inverts_match() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    new_ucmd!()
+        .args(&["./simple", "!", "-name", "a*c"])
+        .succeeds()
+        .no_stdout()
+        .no_stderr();
+}
+
+#[test]
+fn test_parenthesized_grouping() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    new_ucmd!()
+        .args(&["./simple", "(", "-name", "a*c", "-o", "-name", "zzz", ")"])
+        .succeeds()
+        .stdout_only("./simple/abbc");
+}
+
+#[test]
+fn test_maxdepth_zero_excludes_directory_contents() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    new_ucmd!()
+        .args(&["./simple", "-maxdepth", "0"])
+        .succeeds()
+        .no_stdout()
+        .no_stderr();
+}
+
+#[test]
+fn test_mindepth_one_includes_direct_children() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    new_ucmd!()
+        .args(&["./simple", "-mindepth", "1"])
+        .succeeds()
+        .stdout_only("./simple/abbc");
+}
+
+#[test]
+fn test_exec_plus_requires_trailing_placeholder() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    new_ucmd!()
+        .args(&["./simple", "-name", "a*c", "-exec", "echo", "FLAG", "+"])
+        .fails()
+        .stderr_is("Error: -exec ... + must have exactly one '{}', as its last argument");
+}
+
+#[test]
+fn test_color_always_does_not_double_leading_separator() {
+
+	println!("CWD is {}", env::current_dir().unwrap().display());
+    let abs_simple = env::current_dir().unwrap().join("simple").display().to_string();
+    new_ucmd!()
+        .args(&[&abs_simple, "-name", "a*c", "-color", "always"])
+        .succeeds()
+        .stdout_does_not_contain("//");
+}
\ No newline at end of file